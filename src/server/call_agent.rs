@@ -1,6 +1,4 @@
-
-use reqwest;
-use serde;
+use std::time::Duration;
 
 #[derive(serde::Serialize, serde::Deserialize)]
 struct Message {
@@ -60,8 +58,19 @@ const HEADER_ANTHROPIC_VERSION_VALUE: &str = "2023-06-01";
 
 const OLLAMA_API_URL: &str = "http://localhost:11434/api/chat";
 
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Client with timeouts so a stalled provider can't hang a request (or shutdown) forever.
+fn http_client() -> reqwest::Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+}
+
 pub async fn call_openai(prompt: &str, token: &str, model: &str) -> Result<String, Box<dyn std::error::Error>> {
-    let client = reqwest::Client::new();
+    let client = http_client()?;
 
     let request_body = RequestBody {
         model: model.to_string(),
@@ -94,7 +103,7 @@ pub async fn call_openai(prompt: &str, token: &str, model: &str) -> Result<Strin
 }
 
 pub async fn call_anthropic(prompt: &str, token: &str, model: &str) -> Result<String, Box<dyn std::error::Error>> {
-    let client = reqwest::Client::new();
+    let client = http_client()?;
 
     let request_body = AnthropicRequestBody {
         model: model.to_string(),
@@ -130,7 +139,7 @@ pub async fn call_anthropic(prompt: &str, token: &str, model: &str) -> Result<St
 }
 
 pub async fn call_ollama(prompt: &str, model: &str) -> Result<String, Box<dyn std::error::Error>> {
-    let client = reqwest::Client::new();
+    let client = http_client()?;
 
     let request_body = RequestBody {
         model: model.to_string(),
@@ -0,0 +1,308 @@
+use std::env;
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use serde::Deserialize;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+use crate::logging;
+use crate::secrets;
+
+const CONFIG_FILENAME: &str = "mbot.toml";
+const CONFIG_DIR_NAME: &str = "mbot";
+
+const DEFAULT_PORT: u16 = 6411;
+const DEFAULT_SQLITE_FILENAME: &str = "agents.sqlite";
+const DEFAULT_AGENTS_FOLDER: &str = "workspace";
+const DEFAULT_LOG_MAX_FILES: usize = 14;
+
+const ENV_CONFIG_PATH: &str = "MBOT_CONFIG";
+const ENV_PORT: &str = "PORT";
+const ENV_SQLITE_FILENAME: &str = "SQLITE_FILENAME";
+const ENV_AGENTS_FOLDER: &str = "AGENTS_FOLDER";
+const ENV_LOG_FORMAT: &str = "LOG_FORMAT";
+const ENV_LOG_LEVEL: &str = "LOG_LEVEL";
+const ENV_LOG_DIR: &str = "LOG_DIR";
+const ENV_LOG_MAX_FILES: &str = "LOG_MAX_FILES";
+
+/// Config shared with handlers and replaced in place on reload.
+pub type SharedConfig = Arc<RwLock<Config>>;
+
+/// Server settings read from `mbot.toml`, with environment variables taking precedence.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub port: u16,
+    pub sqlite_filename: String,
+    pub agents_folder: String,
+    pub log_format: LogFormat,
+    /// Most verbose level logged; the only setting applied on reload.
+    pub log_level: LogLevel,
+    /// Directory for daily-rotated log files; logs go to stdout when unset.
+    pub log_dir: Option<String>,
+    /// Rotated log files kept in `log_dir`; `0` keeps all of them.
+    pub log_max_files: usize,
+    /// File the config was read from, if any.
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            port: DEFAULT_PORT,
+            sqlite_filename: DEFAULT_SQLITE_FILENAME.to_string(),
+            agents_folder: DEFAULT_AGENTS_FOLDER.to_string(),
+            log_format: LogFormat::default(),
+            log_level: LogLevel::default(),
+            log_dir: None,
+            log_max_files: DEFAULT_LOG_MAX_FILES,
+            source: None,
+        }
+    }
+}
+
+impl Config {
+    /// Loads the config file (if any) and applies environment overrides.
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        let mut config = match find_config_file() {
+            Some(path) => {
+                let raw = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("Failed to read config {}: {}", path.display(), e))?;
+                let mut config: Config = toml::from_str(&raw)
+                    .map_err(|e| format!("Invalid config {}: {}", path.display(), e))?;
+                config
+                    .expand_env_references()
+                    .map_err(|e| format!("Invalid config {}: {}", path.display(), e))?;
+                config.source = Some(path);
+                config
+            }
+            None => Config::default(),
+        };
+        config.apply_env_overrides(|name| env::var(name).ok())?;
+        Ok(config)
+    }
+
+    /// Connection string for the SQLite pool.
+    pub fn sqlite_conn(&self) -> String {
+        format!("sqlite:{}", self.sqlite_filename)
+    }
+
+    /// Applies a re-read config to the running one. Only `log_level` takes effect immediately;
+    /// the rest is bound at startup (existing agents' folders live under the original
+    /// `agents_folder`, so moving the root at runtime would orphan them on removal), so changes
+    /// to those are logged until the next restart.
+    fn apply_reload(&mut self, new: Config) {
+        if new.port != self.port {
+            warn!("Ignoring port change to {} until restart", new.port);
+        }
+        if new.sqlite_filename != self.sqlite_filename {
+            warn!(
+                "Ignoring sqlite_filename change to {} until restart",
+                new.sqlite_filename
+            );
+        }
+        if new.agents_folder != self.agents_folder {
+            warn!(
+                "Ignoring agents_folder change to {} until restart",
+                new.agents_folder
+            );
+        }
+        if new.log_format != self.log_format
+            || new.log_dir != self.log_dir
+            || new.log_max_files != self.log_max_files
+        {
+            warn!("Ignoring logging changes until restart");
+        }
+        if new.log_level != self.log_level {
+            logging::set_level(new.log_level);
+            info!("Log level changed to {:?}", new.log_level);
+            self.log_level = new.log_level;
+        }
+        self.source = new.source;
+    }
+
+    /// Expands `${NAME}` in the string settings. Done after parsing so comments are ignored and
+    /// variable values can't alter the TOML structure.
+    fn expand_env_references(&mut self) -> Result<(), String> {
+        self.sqlite_filename = secrets::expand_env(&self.sqlite_filename)?;
+        self.agents_folder = secrets::expand_env(&self.agents_folder)?;
+        if let Some(dir) = &self.log_dir {
+            self.log_dir = Some(secrets::expand_env(dir)?);
+        }
+        Ok(())
+    }
+
+    fn apply_env_overrides(
+        &mut self,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<(), Box<dyn Error>> {
+        if let Some(port) = lookup(ENV_PORT) {
+            self.port = port
+                .parse()
+                .map_err(|e| format!("Invalid {ENV_PORT} '{port}': {e}"))?;
+        }
+        if let Some(filename) = lookup(ENV_SQLITE_FILENAME) {
+            self.sqlite_filename = filename;
+        }
+        if let Some(folder) = lookup(ENV_AGENTS_FOLDER) {
+            self.agents_folder = folder;
+        }
+        if let Some(format) = lookup(ENV_LOG_FORMAT) {
+            self.log_format = match format.to_lowercase().as_str() {
+                "text" => LogFormat::Text,
+                "json" => LogFormat::Json,
+                _ => {
+                    return Err(format!(
+                        "Invalid {ENV_LOG_FORMAT} '{format}': expected text or json"
+                    )
+                    .into());
+                }
+            };
+        }
+        if let Some(level) = lookup(ENV_LOG_LEVEL) {
+            self.log_level = match level.to_lowercase().as_str() {
+                "error" => LogLevel::Error,
+                "warn" => LogLevel::Warn,
+                "info" => LogLevel::Info,
+                "debug" => LogLevel::Debug,
+                "trace" => LogLevel::Trace,
+                _ => {
+                    return Err(format!(
+                        "Invalid {ENV_LOG_LEVEL} '{level}': expected error, warn, info, debug or trace"
+                    )
+                    .into());
+                }
+            };
+        }
+        if let Some(dir) = lookup(ENV_LOG_DIR) {
+            self.log_dir = Some(dir);
+        }
+        if let Some(max_files) = lookup(ENV_LOG_MAX_FILES) {
+            self.log_max_files = max_files
+                .parse()
+                .map_err(|e| format!("Invalid {ENV_LOG_MAX_FILES} '{max_files}': {e}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Re-reads the config file and environment into `shared`.
+pub async fn reload(shared: &SharedConfig) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let new = Config::load().map_err(|e| e.to_string())?;
+    match &new.source {
+        Some(path) => info!("Config reloaded from {}", path.display()),
+        None => info!("Config reloaded from environment"),
+    }
+    shared.write().await.apply_reload(new);
+    Ok(())
+}
+
+/// Looks for the config in `$MBOT_CONFIG`, then the current directory, then the XDG config dir.
+fn find_config_file() -> Option<PathBuf> {
+    if let Ok(path) = env::var(ENV_CONFIG_PATH) {
+        return Some(PathBuf::from(path));
+    }
+
+    let local = PathBuf::from(CONFIG_FILENAME);
+    if local.exists() {
+        return Some(local);
+    }
+
+    let config_home = env::var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|_| env::var("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .ok()?;
+    let xdg = config_home.join(CONFIG_DIR_NAME).join(CONFIG_FILENAME);
+    xdg.exists().then_some(xdg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overrides(vars: &[(&str, &str)]) -> Result<Config, Box<dyn Error>> {
+        let mut config = Config::default();
+        config.apply_env_overrides(|name| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        })?;
+        Ok(config)
+    }
+
+    #[test]
+    fn test_apply_env_overrides_sets_fields() {
+        let config = overrides(&[
+            (ENV_PORT, "7000"),
+            (ENV_LOG_FORMAT, "JSON"),
+            (ENV_LOG_LEVEL, "Debug"),
+            (ENV_LOG_MAX_FILES, "3"),
+        ])
+        .unwrap();
+        assert_eq!(config.port, 7000);
+        assert_eq!(config.log_format, LogFormat::Json);
+        assert_eq!(config.log_level, LogLevel::Debug);
+        assert_eq!(config.log_max_files, 3);
+    }
+
+    #[test]
+    fn test_apply_env_overrides_rejects_invalid_port() {
+        let err = overrides(&[(ENV_PORT, "not-a-port")]).unwrap_err();
+        assert!(err.to_string().starts_with("Invalid PORT"), "{err}");
+    }
+
+    #[test]
+    fn test_apply_env_overrides_rejects_invalid_log_format() {
+        let err = overrides(&[(ENV_LOG_FORMAT, "xml")]).unwrap_err();
+        assert!(err.to_string().starts_with("Invalid LOG_FORMAT"), "{err}");
+    }
+
+    #[test]
+    fn test_apply_reload_updates_log_level_only() {
+        let mut running = Config::default();
+        let new = Config {
+            port: 7002,
+            log_level: LogLevel::Debug,
+            ..Config::default()
+        };
+        running.apply_reload(new);
+        assert_eq!(running.log_level, LogLevel::Debug);
+        assert_eq!(running.port, DEFAULT_PORT);
+    }
+
+    #[test]
+    fn test_config_rejects_unknown_keys() {
+        let err = toml::from_str::<Config>("prot = 7000\n").unwrap_err();
+        assert!(err.to_string().contains("unknown field `prot`"), "{err}");
+    }
+
+    #[test]
+    fn test_config_ignores_references_in_comments() {
+        let mut config: Config =
+            toml::from_str("# token = \"${MBOT_TEST_UNSET_VAR}\"\nport = 7001\n").unwrap();
+        config.expand_env_references().unwrap();
+        assert_eq!(config.port, 7001);
+    }
+}
@@ -14,6 +14,8 @@ const SQL_CREATE_PROMPT_TABLE: &str = "CREATE TABLE IF NOT EXISTS prompts (
 const SQL_INSERT_PROMPT: &str = "INSERT INTO prompts (prompt, agent_id, user_prompt, created_at) VALUES (?, ?, ?, ?)";
 
 const SQL_SELECT_AGENT_ALL: &str = "SELECT id, name, token, model, brand, status, created_at FROM agents";
+const SQL_PING: &str = "SELECT 1";
+const SQL_COUNT_AGENTS: &str = "SELECT COUNT(*) FROM agents";
 const SQL_SELECT_AGENT_BY_ID: &str = "SELECT id, name, token, model, brand, status, created_at FROM agents WHERE id = ?";
 const SQL_DELETE_AGENT_BY_ID: &str = "DELETE FROM agents WHERE id = ?";
 const SQL_INSERT_AGENT: &str = "INSERT INTO agents (name, token, model, brand, status, created_at) VALUES (?, ?, ?, ?, ?, ?)";
@@ -45,6 +47,15 @@ pub async fn list_agents(pool: &SqlitePool) -> Result<Vec<Agent>, sqlx::Error> {
         .await
 }
 
+pub async fn ping_db(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    sqlx::query(SQL_PING).execute(pool).await?;
+    Ok(())
+}
+
+pub async fn count_agents(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(SQL_COUNT_AGENTS).fetch_one(pool).await
+}
+
 pub async fn get_agent_by_id(pool: &SqlitePool, id: i64) -> Result<Option<Agent>, sqlx::Error> {
     sqlx::query_as::<_, Agent>(SQL_SELECT_AGENT_BY_ID)
         .bind(id)
@@ -1,16 +1,62 @@
-use axum::{extract::State, http::StatusCode, response::Json};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
 
-use chrono::Utc;
+use axum::{
+    extract::{FromRef, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Json},
+};
+
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
 use tokio::fs;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
-use crate::db_func;
 use crate::call_agent;
+use crate::config::{self, SharedConfig};
+use crate::db_func;
+use crate::metrics::Metrics;
+use crate::secrets;
 
-const AGENTS_FOLDER: &str = "workspace";
 const MSG_SUCCESS: &str = "Agent created successfully";
+const MSG_INVALID_AGENT_NAME: &str = "Agent name must be a single folder name";
+const MSG_CONFIG_RELOADED: &str = "Config reloaded";
+const MSG_CONFIG_RELOAD_FAILED: &str = "Failed to reload config, see server log";
+const METRICS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Shared router state; handlers extract the parts they need via `FromRef`.
+#[derive(Clone)]
+pub(crate) struct AppState {
+    pub(crate) pool: SqlitePool,
+    pub(crate) config: SharedConfig,
+    pub(crate) metrics: Arc<Metrics>,
+    pub(crate) started_at: DateTime<Utc>,
+}
+
+impl FromRef<AppState> for SqlitePool {
+    fn from_ref(state: &AppState) -> Self {
+        state.pool.clone()
+    }
+}
+
+impl FromRef<AppState> for SharedConfig {
+    fn from_ref(state: &AppState) -> Self {
+        state.config.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<Metrics> {
+    fn from_ref(state: &AppState) -> Self {
+        state.metrics.clone()
+    }
+}
+
+impl FromRef<AppState> for DateTime<Utc> {
+    fn from_ref(state: &AppState) -> Self {
+        state.started_at
+    }
+}
 
 #[derive(Debug, Clone, sqlx::FromRow, Serialize, Deserialize)]
 pub struct Agent {
@@ -89,37 +135,82 @@ pub async fn list_handler(State(pool): State<SqlitePool>) -> Result<Json<ListRes
     }
 }
 
-pub async fn prompt_handler(State(pool): State<SqlitePool>, Json(payload): Json<DataAgent>) -> Result<Json<AgentResponse>, (StatusCode, Json<ErrorResponse>)> {
+pub async fn prompt_handler(
+    State(pool): State<SqlitePool>,
+    State(metrics): State<Arc<Metrics>>,
+    Json(payload): Json<DataAgent>,
+) -> Result<Json<AgentResponse>, (StatusCode, Json<ErrorResponse>)> {
     let agent_result = db_func::get_agent_by_id(&pool, payload.id).await;
 
     match agent_result {
         Ok(Some(agent)) => {
+            metrics.prompt_received();
+            let token = match secrets::expand_agent_token(&agent.token) {
+                Ok(token) => token,
+                Err(e) => {
+                    error!("Failed to resolve token for agent {}: {}", agent.id, e);
+                    return Err((
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(ErrorResponse {
+                            error: format!("Failed to resolve token for agent {}", agent.id),
+                        }),
+                    ));
+                }
+            };
             if agent.brand == "openai" {
-                match call_agent::call_openai(&payload.prompt, &agent.token, &agent.model).await {
+                match call_agent::call_openai(&payload.prompt, &token, &agent.model).await {
                     Ok(response) => {
-                        info!("OpenAI response: {}", response);
+                        metrics.provider_call(&agent.brand, true);
+                        info!(
+                            agent_id = agent.id,
+                            brand = %agent.brand,
+                            outcome = "ok",
+                            "OpenAI response: {}",
+                            response,
+                        );
                     }
                     Err(e) => {
-                        error!("Failed to call OpenAI: {}", e);
+                        metrics.provider_call(&agent.brand, false);
+                        error!(
+                            agent_id = agent.id,
+                            brand = %agent.brand,
+                            outcome = "error",
+                            "Failed to call OpenAI: {}",
+                            e,
+                        );
                         return Err((
                             StatusCode::INTERNAL_SERVER_ERROR,
                             Json(ErrorResponse {
-                                error: format!("Failed to call OpenAI: {}", e),
+                                error: "Failed to call OpenAI".to_string(),
                             }),
                         ));
                     }
                 }
             } else if agent.brand == "anthropic" {
-                match call_agent::call_anthropic(&payload.prompt, &agent.token, &agent.model).await {
+                match call_agent::call_anthropic(&payload.prompt, &token, &agent.model).await {
                     Ok(response) => {
-                        info!("Anthropic response: {}", response);
+                        metrics.provider_call(&agent.brand, true);
+                        info!(
+                            agent_id = agent.id,
+                            brand = %agent.brand,
+                            outcome = "ok",
+                            "Anthropic response: {}",
+                            response,
+                        );
                     }
                     Err(e) => {
-                        error!("Failed to call Anthropic: {}", e);
+                        metrics.provider_call(&agent.brand, false);
+                        error!(
+                            agent_id = agent.id,
+                            brand = %agent.brand,
+                            outcome = "error",
+                            "Failed to call Anthropic: {}",
+                            e,
+                        );
                         return Err((
                             StatusCode::INTERNAL_SERVER_ERROR,
                             Json(ErrorResponse {
-                                error: format!("Failed to call Anthropic: {}", e),
+                                error: "Failed to call Anthropic".to_string(),
                             }),
                         ));
                     }
@@ -127,14 +218,28 @@ pub async fn prompt_handler(State(pool): State<SqlitePool>, Json(payload): Json<
             } else if agent.brand == "ollama" {
                 match call_agent::call_ollama(&payload.prompt, &agent.model).await {
                     Ok(response) => {
-                        info!("Ollama response: {}", response);
+                        metrics.provider_call(&agent.brand, true);
+                        info!(
+                            agent_id = agent.id,
+                            brand = %agent.brand,
+                            outcome = "ok",
+                            "Ollama response: {}",
+                            response,
+                        );
                     }
                     Err(e) => {
-                        error!("Failed to call Ollama: {}", e);
+                        metrics.provider_call(&agent.brand, false);
+                        error!(
+                            agent_id = agent.id,
+                            brand = %agent.brand,
+                            outcome = "error",
+                            "Failed to call Ollama: {}",
+                            e,
+                        );
                         return Err((
                             StatusCode::INTERNAL_SERVER_ERROR,
                             Json(ErrorResponse {
-                                error: format!("Failed to call Ollama: {}", e),
+                                error: "Failed to call Ollama".to_string(),
                             }),
                         ));
                     }
@@ -182,15 +287,26 @@ pub async fn process_handler(State(pool): State<SqlitePool>) -> Result<Json<List
 }
 pub async fn add_agent_handler(
     State(pool): State<SqlitePool>,
+    State(config): State<SharedConfig>,
+    State(metrics): State<Arc<Metrics>>,
     Json(payload): Json<CreateAgent>,
 ) -> Result<Json<CreateAgentResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let agents_folder = config.read().await.agents_folder.clone();
+    let Some(folder_path) = agent_folder(&agents_folder, &payload.name) else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: MSG_INVALID_AGENT_NAME.to_string(),
+            }),
+        ));
+    };
 
     let result = db_func::insert_agent(&pool, &payload).await;
 
     match result {
         Ok(query_result) => {
-
-            gen_agent_folder(&payload).await;
+            metrics.agent_created();
+            gen_agent_folder(&folder_path, &payload).await;
 
             Ok(Json(CreateAgentResponse {
                 id: query_result.last_insert_rowid(),
@@ -211,6 +327,8 @@ pub async fn add_agent_handler(
 
 pub async fn remove_agent_handler(
     State(pool): State<SqlitePool>,
+    State(config): State<SharedConfig>,
+    State(metrics): State<Arc<Metrics>>,
     Json(payload): Json<DataAgent>,
 ) -> Result<Json<RemoveAgentResponse>, (StatusCode, Json<ErrorResponse>)> {
     let agent_result = db_func::get_agent_by_id(&pool, payload.id).await;
@@ -218,16 +336,25 @@ pub async fn remove_agent_handler(
     match agent_result {
         Ok(Some(agent)) => {
             // Delete the folder & file
-            let folder_path = format!("./{}/{}", AGENTS_FOLDER, agent.name);
-            if let Err(e) = fs::remove_dir_all(&folder_path).await {
-                error!("Failed to remove folder for agent {}: {}", agent.name, e);
-            } else {
-                info!("Removed folder for agent: {}", folder_path);
+            let agents_folder = config.read().await.agents_folder.clone();
+            match agent_folder(&agents_folder, &agent.name) {
+                Some(folder_path) => {
+                    if let Err(e) = fs::remove_dir_all(&folder_path).await {
+                        error!("Failed to remove folder for agent {}: {}", agent.name, e);
+                    } else {
+                        info!("Removed folder for agent: {}", folder_path.display());
+                    }
+                }
+                None => warn!(
+                    "Not removing folder for agent {}: name is outside {}",
+                    agent.name, agents_folder
+                ),
             }
 
             let delete_result = db_func::delete_agent_by_id(&pool, payload.id).await;
             match delete_result {
                 Ok(_) => {
+                    metrics.agent_removed();
                     Ok(Json(RemoveAgentResponse {
                         message: format!("Agent {} removed successfully", payload.id),
                     }))
@@ -263,12 +390,23 @@ pub async fn remove_agent_handler(
     }
 }
 
-async fn gen_agent_folder(payload: &CreateAgent) {
-    let folder_path = format!("./{}/{}", AGENTS_FOLDER, payload.name);
-    if let Err(e) = fs::create_dir_all(&folder_path).await {
+/// Folder for the agent `name` inside `agents_folder`, or `None` unless `name` is a single plain
+/// path component (no separators, `..` or absolute prefix) that stays inside the folder.
+fn agent_folder(agents_folder: &str, name: &str) -> Option<PathBuf> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(part)), None) if part == name => {
+            Some(Path::new(agents_folder).join(part))
+        }
+        _ => None,
+    }
+}
+
+async fn gen_agent_folder(folder_path: &Path, payload: &CreateAgent) {
+    if let Err(e) = fs::create_dir_all(folder_path).await {
         error!("Failed to create folder for agent {}: {}", payload.name, e);
     } else {
-        info!("Created folder for agent: {}", folder_path);
+        info!("Created folder for agent: {}", folder_path.display());
     }
 
     let created_at = Utc::now().to_rfc3339();
@@ -278,11 +416,11 @@ async fn gen_agent_folder(payload: &CreateAgent) {
 "#, payload.name, payload.model, &created_at
     );
 
-    let readme_path = format!("{}/readme.md", folder_path);
+    let readme_path = folder_path.join("readme.md");
     if let Err(e) = fs::write(&readme_path, &readme_content).await {
         error!("Failed to create readme for agent {}: {}", payload.name, e);
     } else {
-        info!("Created readme file for agent: {}", readme_path);
+        info!("Created readme file for agent: {}", readme_path.display());
     }
 }
 
@@ -297,6 +435,102 @@ pub async fn compatible_client_version_handler() -> Json<VersionResponse> {
     })
 }
 
+pub async fn metrics_handler(
+    State(pool): State<SqlitePool>,
+    State(metrics): State<Arc<Metrics>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    match db_func::count_agents(&pool).await {
+        Ok(agents) => Ok((
+            [(header::CONTENT_TYPE, METRICS_CONTENT_TYPE)],
+            metrics.render(agents),
+        )),
+        Err(e) => {
+            error!("Failed to count agents: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("Failed to count agents: {}", e),
+                }),
+            ))
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub(crate) struct ReloadResponse {
+    message: String,
+}
+
+pub async fn reload_config_handler(
+    State(config): State<SharedConfig>,
+) -> Result<Json<ReloadResponse>, (StatusCode, Json<ErrorResponse>)> {
+    match config::reload(&config).await {
+        Ok(()) => Ok(Json(ReloadResponse {
+            message: MSG_CONFIG_RELOADED.to_string(),
+        })),
+        Err(e) => {
+            error!("Failed to reload config: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: MSG_CONFIG_RELOAD_FAILED.to_string(),
+                }),
+            ))
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub(crate) struct HealthResponse {
+    status: String,
+    database: String,
+    started_at: String,
+    uptime_seconds: i64,
+}
+
+pub async fn health_handler(
+    State(pool): State<SqlitePool>,
+    State(started_at): State<DateTime<Utc>>,
+) -> (StatusCode, Json<HealthResponse>) {
+    let (code, status) = match db_func::ping_db(&pool).await {
+        Ok(()) => (StatusCode::OK, "ok"),
+        Err(e) => {
+            error!("Health check failed: {}", e);
+            (StatusCode::SERVICE_UNAVAILABLE, "error")
+        }
+    };
+
+    (
+        code,
+        Json(HealthResponse {
+            status: status.to_string(),
+            database: status.to_string(),
+            started_at: started_at.to_rfc3339(),
+            uptime_seconds: (Utc::now() - started_at).num_seconds(),
+        }),
+    )
+}
+
 pub async fn ping_handler() -> StatusCode {
     StatusCode::OK
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_agent_folder_joins_plain_name() {
+        assert_eq!(
+            agent_folder("workspace", "bot-1"),
+            Some(PathBuf::from("workspace/bot-1"))
+        );
+    }
+
+    #[test]
+    fn test_agent_folder_rejects_paths() {
+        for name in ["", ".", "..", "/home", "../etc", "a/b", "bot/", "./bot"] {
+            assert_eq!(agent_folder("workspace", name), None, "{name:?}");
+        }
+    }
+}
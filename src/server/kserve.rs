@@ -2,56 +2,181 @@ use axum::{
     routing::{delete, get, post},
     Router,
 };
+use dotenv::dotenv;
 use sqlx::SqlitePool;
 use std::error::Error;
-use tracing::info;
+use std::future::IntoFuture;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::{error, info};
+
+mod config;
+use config::{Config, SharedConfig};
+
+mod logging;
 
 mod db_func;
 use db_func::create_table_if_not_exists;
 
 mod handler;
 mod call_agent;
-use handler::list_handler;
-use handler::process_handler;
+mod metrics;
+use metrics::Metrics;
+mod secrets;
+mod systemd;
+use handler::AppState;
 use handler::add_agent_handler;
-use handler::remove_agent_handler;
-use handler::prompt_handler;
 use handler::compatible_client_version_handler;
+use handler::health_handler;
+use handler::list_handler;
+use handler::metrics_handler;
 use handler::ping_handler;
+use handler::process_handler;
+use handler::prompt_handler;
+use handler::reload_config_handler;
+use handler::remove_agent_handler;
 
-const PORT: u16 = 6411;
-
-const SQLITE_FILENAME: &str = "agents.sqlite";
-const SQLITE_CONN: &str = "sqlite:agents.sqlite";
 const MSG_SERVER_STARTED: &str = "kserve HTTP server listening on ";
 const MSG_CREATED_DB: &str = "created file database";
 const MSG_DB_INITIALIZED: &str = "Database table initialized";
+const MSG_SHUTTING_DOWN: &str = "Shutdown signal received, draining connections";
+const MSG_SERVER_STOPPED: &str = "kserve stopped";
+const MSG_DRAIN_TIMED_OUT: &str = "Timed out waiting for in-flight requests, exiting";
+const SHUTDOWN_DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    tracing_subscriber::fmt::init();
+    dotenv().ok();
 
-    if !std::path::Path::new(SQLITE_FILENAME).exists() {
-        println!("{MSG_CREATED_DB}");
-        std::fs::File::create(SQLITE_FILENAME)?;
+    let config = Config::load()?;
+    let _log_guard = logging::init(&config)?;
+    if let Some(path) = &config.source {
+        info!("Loaded config from {}", path.display());
     }
-    let pool = SqlitePool::connect(SQLITE_CONN).await?;
+
+    if !std::path::Path::new(&config.sqlite_filename).exists() {
+        info!("{MSG_CREATED_DB}");
+        std::fs::File::create(&config.sqlite_filename)?;
+    }
+    let pool = SqlitePool::connect(&config.sqlite_conn()).await?;
     create_table_if_not_exists(&pool).await?;
     info!("{MSG_DB_INITIALIZED}");
 
+    let addr: std::net::SocketAddr = ([0, 0, 0, 0], config.port).into();
+    let shared_config: SharedConfig = Arc::new(tokio::sync::RwLock::new(config));
+    spawn_reload_on_sighup(shared_config.clone());
+
     let app = Router::new()
         .route("/ping", get(ping_handler))
+        .route("/healthz", get(health_handler))
         .route("/list", get(list_handler))
         .route("/process", get(process_handler))
         .route("/prompt", get(prompt_handler))
         .route("/add", post(add_agent_handler))
         .route("/remove", delete(remove_agent_handler))
         .route("/compatible_client_version", get(compatible_client_version_handler))
-        .with_state(pool);
+        .route("/metrics", get(metrics_handler))
+        .route("/reload_config", post(reload_config_handler))
+        .with_state(AppState {
+            pool: pool.clone(),
+            config: shared_config.clone(),
+            metrics: Arc::new(Metrics::default()),
+            started_at: chrono::Utc::now(),
+        });
 
-    let addr: std::net::SocketAddr = ([0, 0, 0, 0], PORT).into();
     info!("{MSG_SERVER_STARTED} {addr}");
     let listener = tokio::net::TcpListener::bind(&addr).await?;
-    axum::serve(listener, app).await?;
+    systemd::notify_ready();
+    systemd::spawn_watchdog(pool.clone());
+
+    let shutdown = Arc::new(Notify::new());
+    let server = axum::serve(listener, app)
+        .with_graceful_shutdown({
+            let shutdown = shutdown.clone();
+            async move {
+                shutdown_signal().await;
+                shutdown.notify_one();
+            }
+        })
+        .into_future();
+    // Once a signal arrives, give in-flight requests SHUTDOWN_DRAIN_TIMEOUT to finish so a
+    // stuck provider call can't hold the process until systemd's SIGKILL.
+    let drain_deadline = async {
+        shutdown.notified().await;
+        tokio::time::sleep(SHUTDOWN_DRAIN_TIMEOUT).await;
+    };
+
+    let drained = tokio::select! {
+        result = server => {
+            result?;
+            true
+        }
+        _ = drain_deadline => false,
+    };
+
+    pool.close().await;
+    if !drained {
+        error!("{MSG_DRAIN_TIMED_OUT}");
+        return Err(MSG_DRAIN_TIMED_OUT.into());
+    }
+    info!("{MSG_SERVER_STOPPED}");
     Ok(())
 }
+
+/// Reloads `mbot.toml` and env overrides whenever the process receives SIGHUP.
+#[cfg(unix)]
+fn spawn_reload_on_sighup(config: SharedConfig) {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut sighup = match signal(SignalKind::hangup()) {
+        Ok(sighup) => sighup,
+        Err(e) => {
+            error!("Failed to listen for SIGHUP: {}", e);
+            return;
+        }
+    };
+    tokio::spawn(async move {
+        while sighup.recv().await.is_some() {
+            if let Err(e) = config::reload(&config).await {
+                error!("Failed to reload config: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(not(unix))]
+fn spawn_reload_on_sighup(_config: SharedConfig) {}
+
+/// Resolves on Ctrl+C or SIGTERM so in-flight requests can finish before exit.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    info!("{MSG_SHUTTING_DOWN}");
+    systemd::notify_stopping();
+}
@@ -0,0 +1,72 @@
+use std::error::Error;
+use std::sync::OnceLock;
+
+use tracing::level_filters::LevelFilter;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{Registry, reload};
+
+use crate::config::{Config, LogFormat, LogLevel};
+
+const LOG_FILE_PREFIX: &str = "kserve.log";
+
+/// Handle to the installed level filter, set once by [`init`].
+static LEVEL: OnceLock<reload::Handle<LevelFilter, Registry>> = OnceLock::new();
+
+/// Installs the global subscriber in the configured format. When `log_dir` is set, output goes
+/// to a daily-rotated file there, keeping the newest `log_max_files`; the returned guard must
+/// live until exit to flush it. Fails if the log directory cannot be created.
+pub fn init(config: &Config) -> Result<Option<WorkerGuard>, Box<dyn Error>> {
+    let (writer, guard, ansi) = match &config.log_dir {
+        Some(dir) => {
+            let mut builder = RollingFileAppender::builder()
+                .rotation(Rotation::DAILY)
+                .filename_prefix(LOG_FILE_PREFIX);
+            if config.log_max_files > 0 {
+                builder = builder.max_log_files(config.log_max_files);
+            }
+            let appender = builder
+                .build(dir)
+                .map_err(|e| format!("Failed to open log directory {}: {}", dir, e))?;
+            let (non_blocking, guard) = tracing_appender::non_blocking(appender);
+            (BoxMakeWriter::new(non_blocking), Some(guard), false)
+        }
+        None => (BoxMakeWriter::new(std::io::stdout), None, true),
+    };
+
+    let (level, handle) = reload::Layer::new(level_filter(config.log_level));
+    let fmt_layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(ansi);
+    let fmt_layer = match config.log_format {
+        LogFormat::Text => fmt_layer.boxed(),
+        LogFormat::Json => fmt_layer.json().boxed(),
+    };
+    tracing_subscriber::registry()
+        .with(level)
+        .with(fmt_layer)
+        .init();
+    let _ = LEVEL.set(handle);
+    Ok(guard)
+}
+
+/// Swaps the level filter of the running subscriber. Does nothing before [`init`].
+pub fn set_level(level: LogLevel) {
+    if let Some(handle) = LEVEL.get()
+        && let Err(e) = handle.reload(level_filter(level))
+    {
+        tracing::error!("Failed to change log level: {}", e);
+    }
+}
+
+fn level_filter(level: LogLevel) -> LevelFilter {
+    match level {
+        LogLevel::Error => LevelFilter::ERROR,
+        LogLevel::Warn => LevelFilter::WARN,
+        LogLevel::Info => LevelFilter::INFO,
+        LogLevel::Debug => LevelFilter::DEBUG,
+        LogLevel::Trace => LevelFilter::TRACE,
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// In-process counters exposed on `/metrics` in the Prometheus text format.
+#[derive(Default)]
+pub struct Metrics {
+    prompts_total: AtomicU64,
+    agents_created_total: AtomicU64,
+    agents_removed_total: AtomicU64,
+    provider_requests: Mutex<BTreeMap<String, u64>>,
+    provider_failures: Mutex<BTreeMap<String, u64>>,
+}
+
+impl Metrics {
+    pub fn prompt_received(&self) {
+        self.prompts_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn agent_created(&self) {
+        self.agents_created_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn agent_removed(&self) {
+        self.agents_removed_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts one call to an LLM provider, and a failure when `ok` is false.
+    pub fn provider_call(&self, brand: &str, ok: bool) {
+        increment(&self.provider_requests, brand);
+        if !ok {
+            increment(&self.provider_failures, brand);
+        }
+    }
+
+    /// Renders all counters plus the current agent count as Prometheus exposition text.
+    pub fn render(&self, agents: i64) -> String {
+        let mut out = String::new();
+        write_metric(
+            &mut out,
+            "mbot_agents",
+            "gauge",
+            "Agents currently registered",
+            agents,
+        );
+        write_metric(
+            &mut out,
+            "mbot_prompts_total",
+            "counter",
+            "Prompts received for existing agents",
+            self.prompts_total.load(Ordering::Relaxed),
+        );
+        write_metric(
+            &mut out,
+            "mbot_agents_created_total",
+            "counter",
+            "Agents created since start",
+            self.agents_created_total.load(Ordering::Relaxed),
+        );
+        write_metric(
+            &mut out,
+            "mbot_agents_removed_total",
+            "counter",
+            "Agents removed since start",
+            self.agents_removed_total.load(Ordering::Relaxed),
+        );
+        write_brand_metric(
+            &mut out,
+            "mbot_provider_requests_total",
+            "LLM provider calls per brand",
+            &self.provider_requests,
+        );
+        write_brand_metric(
+            &mut out,
+            "mbot_provider_failures_total",
+            "Failed LLM provider calls per brand",
+            &self.provider_failures,
+        );
+        out
+    }
+}
+
+fn increment(counters: &Mutex<BTreeMap<String, u64>>, brand: &str) {
+    let mut counters = counters.lock().unwrap_or_else(|e| e.into_inner());
+    *counters.entry(brand.to_string()).or_default() += 1;
+}
+
+fn write_metric(
+    out: &mut String,
+    name: &str,
+    kind: &str,
+    help: &str,
+    value: impl std::fmt::Display,
+) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+    let _ = writeln!(out, "{name} {value}");
+}
+
+fn write_brand_metric(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    counters: &Mutex<BTreeMap<String, u64>>,
+) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} counter");
+    let counters = counters.lock().unwrap_or_else(|e| e.into_inner());
+    for (brand, value) in counters.iter() {
+        let brand = brand
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n");
+        let _ = writeln!(out, "{name}{{brand=\"{brand}\"}} {value}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_reports_counters_and_agent_gauge() {
+        let metrics = Metrics::default();
+        metrics.prompt_received();
+        metrics.agent_created();
+        metrics.provider_call("openai", true);
+        metrics.provider_call("openai", false);
+
+        let out = metrics.render(3);
+        assert!(out.contains("mbot_agents 3\n"));
+        assert!(out.contains("mbot_prompts_total 1\n"));
+        assert!(out.contains("mbot_agents_created_total 1\n"));
+        assert!(out.contains("mbot_agents_removed_total 0\n"));
+        assert!(out.contains("mbot_provider_requests_total{brand=\"openai\"} 2\n"));
+        assert!(out.contains("mbot_provider_failures_total{brand=\"openai\"} 1\n"));
+    }
+
+    #[test]
+    fn test_render_escapes_brand_labels() {
+        let metrics = Metrics::default();
+        metrics.provider_call("a\"b\\c\nd", true);
+
+        let out = metrics.render(0);
+        assert!(out.contains(r#"mbot_provider_requests_total{brand="a\"b\\c\nd"} 1"#));
+    }
+}
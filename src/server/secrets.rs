@@ -0,0 +1,107 @@
+use std::env;
+use std::sync::LazyLock;
+
+use regex::{Captures, Regex};
+
+/// Only variables with this prefix may be referenced from agent tokens, which anyone who
+/// can reach `/add` is able to set.
+pub const AGENT_SECRET_PREFIX: &str = "MBOT_SECRET_";
+
+static ENV_REFERENCE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)\}").expect("valid env reference regex")
+});
+
+/// Replaces every `${NAME}` in `value` with the environment variable `NAME`, so secrets can be
+/// kept out of `mbot.toml`. Fails on the first variable that is not set.
+pub fn expand_env(value: &str) -> Result<String, String> {
+    expand_with(value, "", |name| env::var(name).ok())
+}
+
+/// Like [`expand_env`], but only resolves `MBOT_SECRET_*` variables so a registered token can't
+/// pull arbitrary server environment into provider requests.
+pub fn expand_agent_token(token: &str) -> Result<String, String> {
+    expand_with(token, AGENT_SECRET_PREFIX, |name| env::var(name).ok())
+}
+
+fn expand_with(
+    value: &str,
+    required_prefix: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<String, String> {
+    let mut failure = None;
+    let expanded = ENV_REFERENCE.replace_all(value, |caps: &Captures| {
+        let name = &caps[1];
+        if failure.is_some() {
+            return String::new();
+        }
+        if !name.starts_with(required_prefix) {
+            failure = Some(format!(
+                "environment variable {name} is not allowed (must start with {required_prefix})"
+            ));
+            return String::new();
+        }
+        lookup(name).unwrap_or_else(|| {
+            failure = Some(format!("environment variable {name} is not set"));
+            String::new()
+        })
+    });
+
+    match failure {
+        Some(e) => Err(e),
+        None => Ok(expanded.into_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "MBOT_SECRET_A" => Some("alpha".to_string()),
+            "MBOT_SECRET_B" => Some("beta".to_string()),
+            "HOME" => Some("/root".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_expand_with_replaces_set_variable() {
+        assert_eq!(
+            expand_with("key-${MBOT_SECRET_A}", "", lookup).unwrap(),
+            "key-alpha"
+        );
+    }
+
+    #[test]
+    fn test_expand_with_rejects_unset_variable() {
+        let err = expand_with("${MBOT_SECRET_MISSING}", "", lookup).unwrap_err();
+        assert_eq!(err, "environment variable MBOT_SECRET_MISSING is not set");
+    }
+
+    #[test]
+    fn test_expand_with_replaces_multiple_variables() {
+        let expanded = expand_with("${MBOT_SECRET_A}:${MBOT_SECRET_B}", "", lookup).unwrap();
+        assert_eq!(expanded, "alpha:beta");
+    }
+
+    #[test]
+    fn test_expand_with_leaves_plain_values_untouched() {
+        assert_eq!(
+            expand_with("sk-plain $HOME {x}", "", lookup).unwrap(),
+            "sk-plain $HOME {x}"
+        );
+    }
+
+    #[test]
+    fn test_expand_with_rejects_variables_outside_prefix() {
+        let err = expand_with("${HOME}", AGENT_SECRET_PREFIX, lookup).unwrap_err();
+        assert!(err.contains("not allowed"), "{err}");
+    }
+
+    #[test]
+    fn test_expand_with_allows_variables_with_prefix() {
+        let expanded = expand_with("${MBOT_SECRET_B}", AGENT_SECRET_PREFIX, lookup).unwrap();
+        assert_eq!(expanded, "beta");
+    }
+}
@@ -0,0 +1,135 @@
+use std::env;
+use std::time::Duration;
+
+use sqlx::SqlitePool;
+use tracing::{error, info, warn};
+
+use crate::db_func;
+
+const ENV_NOTIFY_SOCKET: &str = "NOTIFY_SOCKET";
+const ENV_WATCHDOG_USEC: &str = "WATCHDOG_USEC";
+const ENV_WATCHDOG_PID: &str = "WATCHDOG_PID";
+
+/// Tells systemd the server is accepting connections (`Type=notify` units).
+pub fn notify_ready() {
+    notify("READY=1");
+}
+
+/// Tells systemd a shutdown is in progress.
+pub fn notify_stopping() {
+    notify("STOPPING=1");
+}
+
+/// Pings the systemd watchdog at half of `WatchdogSec`, skipping pings while SQLite is
+/// unreachable so a wedged server gets restarted. Does nothing when no watchdog is configured.
+pub fn spawn_watchdog(pool: SqlitePool) {
+    let Some(interval) = watchdog_interval() else {
+        return;
+    };
+    info!("systemd watchdog enabled, pinging every {:?}", interval);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match db_func::ping_db(&pool).await {
+                Ok(()) => notify("WATCHDOG=1"),
+                Err(e) => warn!("Skipping watchdog ping, database unreachable: {}", e),
+            }
+        }
+    });
+}
+
+fn watchdog_interval() -> Option<Duration> {
+    parse_watchdog_interval(
+        env::var(ENV_WATCHDOG_USEC).ok().as_deref(),
+        env::var(ENV_WATCHDOG_PID).ok().as_deref(),
+        std::process::id(),
+    )
+}
+
+/// Half of `WATCHDOG_USEC` (at least 1µs, since `tokio::time::interval` panics on zero), or
+/// `None` when it is unset/zero or `WATCHDOG_PID` names another process.
+fn parse_watchdog_interval(
+    usec: Option<&str>,
+    pid: Option<&str>,
+    own_pid: u32,
+) -> Option<Duration> {
+    let usec: u64 = usec?.parse().ok()?;
+    if let Some(pid) = pid
+        && pid.parse::<u32>().ok() != Some(own_pid)
+    {
+        return None;
+    }
+    (usec > 0).then(|| Duration::from_micros((usec / 2).max(1)))
+}
+
+#[cfg(unix)]
+fn notify(state: &str) {
+    use std::os::unix::net::UnixDatagram;
+
+    let Ok(socket_path) = env::var(ENV_NOTIFY_SOCKET) else {
+        return;
+    };
+
+    let result = UnixDatagram::unbound().and_then(|socket| match socket_path.strip_prefix('@') {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            socket.send_to_addr(state.as_bytes(), &addr)
+        }
+        _ => socket.send_to(state.as_bytes(), &socket_path),
+    });
+
+    if let Err(e) = result {
+        error!("Failed to notify systemd ({}): {}", state, e);
+    }
+}
+
+#[cfg(not(unix))]
+fn notify(_state: &str) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_watchdog_interval_halves_usec() {
+        let interval = parse_watchdog_interval(Some("30000000"), Some("42"), 42);
+        assert_eq!(interval, Some(Duration::from_secs(15)));
+    }
+
+    #[test]
+    fn test_parse_watchdog_interval_without_pid() {
+        let interval = parse_watchdog_interval(Some("2000000"), None, 42);
+        assert_eq!(interval, Some(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_parse_watchdog_interval_rejects_pid_mismatch() {
+        assert_eq!(
+            parse_watchdog_interval(Some("30000000"), Some("7"), 42),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_watchdog_interval_rejects_zero_usec() {
+        assert_eq!(parse_watchdog_interval(Some("0"), None, 42), None);
+    }
+
+    #[test]
+    fn test_parse_watchdog_interval_never_returns_zero() {
+        assert_eq!(
+            parse_watchdog_interval(Some("1"), None, 42),
+            Some(Duration::from_micros(1))
+        );
+    }
+
+    #[test]
+    fn test_parse_watchdog_interval_rejects_missing_or_invalid_usec() {
+        assert_eq!(parse_watchdog_interval(None, None, 42), None);
+        assert_eq!(parse_watchdog_interval(Some("soon"), None, 42), None);
+    }
+}